@@ -8,7 +8,7 @@ use std::path::Path;
 fn main() -> Result<(), failure::Error> {
     let mut watcher = Hotwatch::new()?;
     let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/data.json");
-    watcher.watch(&path, move |event| {
+    watcher.watch(&path, move |event, _root| {
         if let EventKind::Modify(ModifyKind::Data(_)) = event.kind {
             Flow::Exit
         } else {
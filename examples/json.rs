@@ -24,7 +24,7 @@ fn main() -> Result<(), failure::Error> {
     let changed = AtomicBool::new(true).into();
     {
         let changed = Arc::clone(&changed);
-        watcher.watch(&path, move |event| {
+        watcher.watch(&path, move |event, _root| {
             if let EventKind::Modify(ModifyKind::Data(_)) = event.kind {
                 changed.store(true, Ordering::Release);
             }
@@ -1,11 +1,9 @@
 //! Blocking file watching
 
-use crate::{util, Error, Event, RECURSIVE_MODE};
-use notify::Watcher as _;
-use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
+use crate::{util, AnyDebouncer, Error, Event, Watcher, RECURSIVE_MODE};
+use notify_debouncer_full::DebounceEventResult;
 use std::{
-    collections::HashMap,
-    path::{Path, PathBuf},
+    path::Path,
     sync::mpsc::{channel, Receiver},
 };
 
@@ -32,8 +30,8 @@ impl Default for Flow {
 ///
 /// Dropping this will unwatch everything.
 pub struct Hotwatch {
-    debouncer: Debouncer<notify::RecommendedWatcher, FileIdMap>,
-    handlers: HashMap<PathBuf, Box<dyn FnMut(Event) -> Flow>>,
+    debouncer: AnyDebouncer,
+    handlers: util::Handlers<Box<dyn FnMut(Event, &Path) -> Flow>>,
     rx: Receiver<DebounceEventResult>,
 }
 
@@ -69,8 +67,15 @@ impl Hotwatch {
     ///
     /// A delay of over 30 seconds will prevent repetitions of previous events on macOS.
     pub fn new_with_custom_delay(delay: std::time::Duration) -> Result<Self, Error> {
+        Self::new_with_config(delay, Watcher::default())
+    }
+
+    /// Like [`Hotwatch::new_with_custom_delay`], but also lets you choose the [`Watcher`]
+    /// backend, e.g. to force polling on a network filesystem where native watching doesn't
+    /// work.
+    pub fn new_with_config(delay: std::time::Duration, watcher: Watcher) -> Result<Self, Error> {
         let (tx, rx) = channel();
-        let debouncer = new_debouncer(delay, None, tx).map_err(Error::Notify)?;
+        let debouncer = AnyDebouncer::new(delay, watcher, tx).map_err(Error::Notify)?;
         Ok(Self {
             debouncer,
             handlers: Default::default(),
@@ -87,7 +92,8 @@ impl Hotwatch {
     ///
     /// Only the most specific applicable handler will be called. In other words, if you're
     /// watching "dir" and "dir/file1", then only the latter handler will fire for changes to
-    /// `file1`.
+    /// `file1`. Alongside the event, the handler receives the root it was registered under, so a
+    /// single recursive handler can tell which of its roots an event came from.
     ///
     /// # Errors
     ///
@@ -104,9 +110,9 @@ impl Hotwatch {
     ///
     /// let mut hotwatch = Hotwatch::new().expect("hotwatch failed to initialize!");
     /// // Note that this won't actually do anything until you call `hotwatch.run()`!
-    /// hotwatch.watch("README.md", |event: Event| {
+    /// hotwatch.watch("README.md", |event: Event, root| {
     ///     if let EventKind::Modify(ModifyKind::Data(_)) = event.kind {
-    ///         println!("{:?} changed!", event.paths[0]);
+    ///         println!("{:?} changed, under {root:?}!", event.paths[0]);
     ///         Flow::Exit
     ///     } else {
     ///         Flow::Continue
@@ -116,19 +122,100 @@ impl Hotwatch {
     pub fn watch<P, F>(&mut self, path: P, handler: F) -> Result<(), Error>
     where
         P: AsRef<Path>,
-        F: 'static + FnMut(Event) -> Flow,
+        F: 'static + FnMut(Event, &Path) -> Flow,
+    {
+        self.watch_with_mode(path, RECURSIVE_MODE, handler)
+    }
+
+    /// Like [`Self::watch`], but only watches the immediate contents of a directory instead of
+    /// recursing into its subdirectories.
+    ///
+    /// This is useful for watching large directories (e.g. a downloads folder) where you don't
+    /// want to pay the cost of traversing every descendant.
+    ///
+    /// # Errors
+    ///
+    /// Watching will fail if the path can't be read, returning [`Error::Io`].
+    pub fn watch_non_recursive<P, F>(&mut self, path: P, handler: F) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        F: 'static + FnMut(Event, &Path) -> Flow,
+    {
+        self.watch_with_mode(path, notify::RecursiveMode::NonRecursive, handler)
+    }
+
+    /// Like [`Self::watch`], but lets you choose the [`notify::RecursiveMode`] used for this
+    /// path explicitly.
+    ///
+    /// # Errors
+    ///
+    /// Watching will fail if the path can't be read, returning [`Error::Io`].
+    pub fn watch_with_mode<P, F>(
+        &mut self,
+        path: P,
+        mode: notify::RecursiveMode,
+        handler: F,
+    ) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        F: 'static + FnMut(Event, &Path) -> Flow,
     {
         let absolute_path = path.as_ref().canonicalize()?;
-        self.debouncer
-            .watcher()
-            .watch(&absolute_path, RECURSIVE_MODE)?;
-        self.debouncer
-            .cache()
-            .add_root(&absolute_path, RECURSIVE_MODE);
+        self.debouncer.watch(&absolute_path, mode)?;
         self.handlers.insert(absolute_path, Box::new(handler));
         Ok(())
     }
 
+    /// Register a handler for paths matching a glob, e.g. `src/**/*.rs`.
+    ///
+    /// Glob handlers are only consulted once no exact root (as registered via [`Self::watch`])
+    /// matches an event's path, and are tried in the order they were registered. Register
+    /// ignore patterns with [`Self::ignore`] to keep paths away from glob handlers, e.g.
+    /// `target/**`.
+    ///
+    /// Event paths are always absolute, so a relative-looking pattern like `src/**/*.rs` is
+    /// matched against the event's path relative to the current working directory; patterns
+    /// that are themselves absolute are matched as-is.
+    ///
+    /// Since there's no single concrete directory backing a glob, the handler receives the glob
+    /// pattern itself as its root.
+    ///
+    /// Note that this only registers the handler and doesn't establish a filesystem watch;
+    /// events only arrive for paths watched via [`Self::watch`] or its variants. Those register
+    /// an exact root, and an exact root matches every path at or beneath it before any glob is
+    /// tried, so an event under a directory watched that way goes to that directory's handler,
+    /// not to a glob handler.
+    ///
+    /// # Errors
+    ///
+    /// This will fail if `pattern` isn't a valid glob, returning [`Error::Glob`].
+    pub fn watch_glob<F>(&mut self, pattern: &str, handler: F) -> Result<(), Error>
+    where
+        F: 'static + FnMut(Event, &Path) -> Flow,
+    {
+        let pattern = glob::Pattern::new(pattern)?;
+        self.handlers.insert_glob(pattern, Box::new(handler));
+        Ok(())
+    }
+
+    /// Exclude paths matching a glob from triggering a [`Self::watch_glob`] handler, e.g.
+    /// `target/**`.
+    ///
+    /// Like [`Self::watch_glob`], the pattern is matched against the event's path relative to
+    /// the current working directory, falling back to the absolute path.
+    ///
+    /// Ignore patterns only suppress the glob fallback; an exact root registered via
+    /// [`Self::watch`] still wins, since exact roots are always the most specific match.
+    ///
+    /// # Errors
+    ///
+    /// This will fail if `pattern` isn't a valid glob, returning [`Error::Glob`].
+    pub fn ignore(&mut self, pattern: &str) -> Result<(), Error> {
+        let pattern = glob::Pattern::new(pattern)?;
+        self.handlers.ignore(pattern);
+        Ok(())
+    }
+
     /// Stop watching a path.
     ///
     /// # Errors
@@ -137,12 +224,45 @@ impl Hotwatch {
     /// couldn't be unwatched for some platform-specific internal reason.
     pub fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
         let absolute_path = path.as_ref().canonicalize()?;
-        self.debouncer.watcher().unwatch(&absolute_path)?;
-        self.debouncer.cache().remove_root(&absolute_path);
+        self.debouncer.unwatch(&absolute_path)?;
         self.handlers.remove(&absolute_path);
         Ok(())
     }
 
+    /// Immediately dispatch any events currently buffered by the debounce delay, instead of
+    /// waiting for the remaining delay to elapse.
+    ///
+    /// This is useful when you know it's safe to process changes right now and don't want to
+    /// hold onto stale state or race a file lock. Unlike [`Self::run`], this returns once all
+    /// currently buffered events have been dispatched, rather than blocking forever; a handler
+    /// returning [`Flow::Exit`] has no effect here.
+    ///
+    /// The debouncer's `flush` doesn't return until any events it dispatches are already
+    /// sitting in the channel, so draining with `try_recv` here is safe, not a race against its
+    /// worker thread.
+    pub fn flush(&mut self) {
+        self.debouncer.flush();
+        while let Ok(result) = self.rx.try_recv() {
+            match result {
+                Ok(events) => {
+                    for event in events {
+                        util::log_event(&event);
+                        if let Some((handler, root)) =
+                            util::handler_for_event(&event, &mut self.handlers)
+                        {
+                            handler(event, &root);
+                        }
+                    }
+                }
+                Err(errs) => {
+                    for err in errs {
+                        util::log_error(&err);
+                    }
+                }
+            }
+        }
+    }
+
     /// Run handlers in an endless loop, blocking the thread.
     ///
     /// The loop will only exit if a handler returns [`Flow::Exit`].
@@ -153,10 +273,10 @@ impl Hotwatch {
                     Ok(events) => {
                         for event in events {
                             util::log_event(&event);
-                            if let Some(handler) =
+                            if let Some((handler, root)) =
                                 util::handler_for_event(&event, &mut self.handlers)
                             {
-                                if let Flow::Exit = handler(event) {
+                                if let Flow::Exit = handler(event, &root) {
                                     break 'watch;
                                 }
                             }
@@ -176,3 +296,131 @@ impl Hotwatch {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        fs,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
+
+    #[test]
+    fn flush_dispatches_a_pending_write_immediately() {
+        let dir = std::env::temp_dir().join(format!("hotwatch-flush-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("failed to create test dir");
+        let file = dir.join("watched.txt");
+        fs::write(&file, "initial").expect("failed to write test file");
+
+        // A long delay means the write below would never be dispatched within the test's
+        // lifetime without `flush` forcing it through.
+        let mut hotwatch =
+            Hotwatch::new_with_custom_delay(std::time::Duration::from_secs(30)).unwrap();
+        let fired = Arc::new(AtomicBool::new(false));
+        {
+            let fired = Arc::clone(&fired);
+            hotwatch
+                .watch(&file, move |_event, _root| {
+                    fired.store(true, Ordering::SeqCst);
+                    Flow::Continue
+                })
+                .expect("failed to watch file");
+        }
+
+        fs::write(&file, "changed").expect("failed to update test file");
+        // `flush` only forces out whatever's already buffered; it can't make the OS notify us
+        // about the write any sooner. Rather than guess a single sleep that's "surely" long
+        // enough, poll `flush` until it catches the event or we give up.
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !fired.load(Ordering::SeqCst) && std::time::Instant::now() < deadline {
+            hotwatch.flush();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+
+        assert!(
+            fired.load(Ordering::SeqCst),
+            "flush should dispatch the pending write event instead of waiting out the delay"
+        );
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn watch_non_recursive_ignores_grandchild_changes() {
+        let dir =
+            std::env::temp_dir().join(format!("hotwatch-nonrecursive-test-{}", std::process::id()));
+        let sub = dir.join("sub");
+        fs::create_dir_all(&sub).expect("failed to create test dir");
+        let grandchild = sub.join("file.txt");
+        fs::write(&grandchild, "initial").expect("failed to write test file");
+
+        let mut hotwatch =
+            Hotwatch::new_with_custom_delay(std::time::Duration::from_millis(50)).unwrap();
+        let fired = Arc::new(AtomicBool::new(false));
+        {
+            let fired = Arc::clone(&fired);
+            hotwatch
+                .watch_non_recursive(&dir, move |_event, _root| {
+                    fired.store(true, Ordering::SeqCst);
+                    Flow::Continue
+                })
+                .expect("failed to watch dir non-recursively");
+        }
+
+        fs::write(&grandchild, "changed").expect("failed to update test file");
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        hotwatch.flush();
+
+        assert!(
+            !fired.load(Ordering::SeqCst),
+            "watch_non_recursive should not fire for changes to a file in a watched \
+             directory's subdirectory"
+        );
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn poll_backend_still_dispatches_events() {
+        let dir = std::env::temp_dir().join(format!("hotwatch-poll-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("failed to create test dir");
+        let file = dir.join("watched.txt");
+        fs::write(&file, "initial").expect("failed to write test file");
+
+        let mut hotwatch = Hotwatch::new_with_config(
+            std::time::Duration::from_millis(50),
+            Watcher::Poll(std::time::Duration::from_millis(50)),
+        )
+        .expect("hotwatch failed to initialize with the poll backend");
+        let fired = Arc::new(AtomicBool::new(false));
+        {
+            let fired = Arc::clone(&fired);
+            hotwatch
+                .watch(&file, move |_event, _root| {
+                    fired.store(true, Ordering::SeqCst);
+                    Flow::Continue
+                })
+                .expect("failed to watch file");
+        }
+
+        // The poll backend only notices changes on its own interval, so retry the write
+        // instead of hoping a single one lands inside some guessed window.
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !fired.load(Ordering::SeqCst) && std::time::Instant::now() < deadline {
+            fs::write(&file, "changed").expect("failed to update test file");
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            // Blocking handlers only run from `run` or `flush`.
+            hotwatch.flush();
+        }
+
+        assert!(
+            fired.load(Ordering::SeqCst),
+            "the poll watcher backend should still notice and dispatch file changes"
+        );
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,214 @@
+//! A debounce layer built directly on [`notify::Watcher`], instead of on top of
+//! `notify_debouncer_full::Debouncer`.
+//!
+//! `notify_debouncer_full::Debouncer` doesn't expose any way to force its internal timer to
+//! dispatch early, and nothing in its public API promises one exists or is safe to rely on
+//! across versions. Rather than call a method we can't confirm is real, hotwatch buffers raw
+//! `notify` events itself, so that [`Debouncer::flush`] only ever touches state this crate owns.
+//!
+//! One consequence: this gives up `notify_debouncer_full`'s file-id-based rename correlation
+//! (folding a Remove+Create pair for the same file into a single rename event). Raw create and
+//! remove events are forwarded as separate events instead.
+
+use crate::{Event, Watcher};
+use notify::{EventHandler, RecursiveMode, Watcher as _};
+use notify_debouncer_full::DebounceEventResult;
+use std::{
+    path::Path,
+    sync::{mpsc::Sender, Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+enum RawWatcher {
+    Native(notify::RecommendedWatcher),
+    Poll(notify::PollWatcher),
+}
+
+impl RawWatcher {
+    fn watch(&mut self, path: &Path, mode: RecursiveMode) -> notify::Result<()> {
+        match self {
+            Self::Native(watcher) => watcher.watch(path, mode),
+            Self::Poll(watcher) => watcher.watch(path, mode),
+        }
+    }
+
+    fn unwatch(&mut self, path: &Path) -> notify::Result<()> {
+        match self {
+            Self::Native(watcher) => watcher.unwatch(path),
+            Self::Poll(watcher) => watcher.unwatch(path),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Buffer {
+    pending: Vec<Event>,
+    last_event_at: Option<Instant>,
+    flush_requested: bool,
+    shutdown: bool,
+}
+
+struct RawHandler {
+    buffer: Arc<Mutex<Buffer>>,
+    signal: Arc<Condvar>,
+    tx: Sender<DebounceEventResult>,
+}
+
+impl EventHandler for RawHandler {
+    fn handle_event(&mut self, event: notify::Result<notify::Event>) {
+        match event {
+            Ok(event) => {
+                if event.paths.is_empty() {
+                    return;
+                }
+                let mut buffer = self.buffer.lock().expect("debounce mutex poisoned!");
+                let now = Instant::now();
+                buffer.pending.push(Event { event, time: now });
+                buffer.last_event_at = Some(now);
+                self.signal.notify_all();
+            }
+            Err(err) => {
+                let _ = self.tx.send(Err(vec![err]));
+            }
+        }
+    }
+}
+
+/// Buffers raw filesystem events and dispatches them as a batch once `delay` has passed since
+/// the most recent one, mirroring the shape of `notify_debouncer_full::DebounceEventResult`.
+pub(crate) struct Debouncer {
+    watcher: RawWatcher,
+    buffer: Arc<Mutex<Buffer>>,
+    signal: Arc<Condvar>,
+    worker: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Debouncer {
+    pub(crate) fn new(
+        delay: Duration,
+        watcher: Watcher,
+        tx: Sender<DebounceEventResult>,
+    ) -> notify::Result<Self> {
+        let buffer = Arc::new(Mutex::new(Buffer::default()));
+        let signal = Arc::new(Condvar::new());
+        let handler = RawHandler {
+            buffer: Arc::clone(&buffer),
+            signal: Arc::clone(&signal),
+            tx: tx.clone(),
+        };
+
+        let raw_watcher = match watcher {
+            Watcher::Native => RawWatcher::Native(notify::RecommendedWatcher::new(
+                handler,
+                notify::Config::default(),
+            )?),
+            Watcher::Poll(interval) => {
+                let config = notify::Config::default().with_poll_interval(interval);
+                RawWatcher::Poll(notify::PollWatcher::new(handler, config)?)
+            }
+        };
+
+        let worker = std::thread::spawn({
+            let buffer = Arc::clone(&buffer);
+            let signal = Arc::clone(&signal);
+            move || Self::run(delay, &buffer, &signal, &tx)
+        });
+
+        Ok(Self {
+            watcher: raw_watcher,
+            buffer,
+            signal,
+            worker: Some(worker),
+        })
+    }
+
+    fn run(
+        delay: Duration,
+        buffer: &Mutex<Buffer>,
+        signal: &Condvar,
+        tx: &Sender<DebounceEventResult>,
+    ) {
+        loop {
+            let mut guard = buffer.lock().expect("debounce mutex poisoned!");
+            loop {
+                if guard.shutdown {
+                    return;
+                }
+                if guard.flush_requested && guard.pending.is_empty() {
+                    // Nothing to flush; clear the request and keep waiting as usual.
+                    guard.flush_requested = false;
+                    signal.notify_all();
+                }
+                if guard.flush_requested {
+                    break;
+                }
+                guard = match guard.last_event_at {
+                    None => signal.wait(guard).expect("debounce mutex poisoned!"),
+                    Some(last) => match delay.checked_sub(last.elapsed()) {
+                        Some(remaining) if !remaining.is_zero() => signal
+                            .wait_timeout(guard, remaining)
+                            .expect("debounce mutex poisoned!")
+                            .0,
+                        _ => break,
+                    },
+                };
+            }
+            if guard.shutdown {
+                return;
+            }
+
+            let events: Vec<Event> = guard.pending.drain(..).collect();
+            guard.last_event_at = None;
+            drop(guard);
+
+            // Send before clearing `flush_requested`, so that by the time `Self::flush` wakes
+            // up, the events it waited for are already sitting in the channel, ready to read.
+            let disconnected = !events.is_empty() && tx.send(Ok(events)).is_err();
+
+            let mut guard = buffer.lock().expect("debounce mutex poisoned!");
+            guard.flush_requested = false;
+            drop(guard);
+            signal.notify_all();
+
+            if disconnected {
+                return;
+            }
+        }
+    }
+
+    pub(crate) fn watch(&mut self, path: &Path, mode: RecursiveMode) -> notify::Result<()> {
+        self.watcher.watch(path, mode)
+    }
+
+    pub(crate) fn unwatch(&mut self, path: &Path) -> notify::Result<()> {
+        self.watcher.unwatch(path)
+    }
+
+    /// Forces any events currently buffered to be dispatched immediately, instead of waiting
+    /// for the remaining delay to elapse, and blocks until the worker thread has sent them.
+    ///
+    /// Because dispatch happens before the worker clears its flush flag, by the time this
+    /// returns, any events it flushed are already sitting in the channel rather than merely
+    /// "probably about to arrive soon" — there's no grace-period guesswork for callers to do.
+    pub(crate) fn flush(&mut self) {
+        let mut guard = self.buffer.lock().expect("debounce mutex poisoned!");
+        guard.flush_requested = true;
+        self.signal.notify_all();
+        let _ = self
+            .signal
+            .wait_while(guard, |buffer| buffer.flush_requested && !buffer.shutdown)
+            .expect("debounce mutex poisoned!");
+    }
+}
+
+impl Drop for Debouncer {
+    fn drop(&mut self) {
+        let mut guard = self.buffer.lock().expect("debounce mutex poisoned!");
+        guard.shutdown = true;
+        drop(guard);
+        self.signal.notify_all();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
@@ -8,30 +8,60 @@
 //!
 //! (There's also a [`blocking`] mode, in case you're a big fan of blocking.)
 //!
+//! With the `tokio` feature enabled, [`Hotwatch::watch_stream`] offers an opt-in async mode
+//! that forwards events to a [`Stream`](tokio_stream::Stream) instead of a callback, for
+//! applications that would rather not deal with hotwatch's background thread at all.
+//!
 //! Only the latest stable version of Rust is supported.
 
 pub mod blocking;
+mod debounce;
 mod util;
 
-use notify::Watcher as _;
 pub use notify::{self, EventKind};
 pub use notify_debouncer_full::DebouncedEvent as Event;
-use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
+pub use util::WatchedPath;
+use notify_debouncer_full::DebounceEventResult;
 use std::{
-    collections::HashMap,
     path::{Path, PathBuf},
     sync::{
-        mpsc::{channel, Receiver},
+        mpsc::{channel, Receiver, Sender},
         Arc, Mutex,
     },
+    time::Duration,
 };
 
 const RECURSIVE_MODE: notify::RecursiveMode = notify::RecursiveMode::Recursive;
 
+/// Selects which `notify` backend is used to watch the filesystem.
+///
+/// The default, [`Watcher::Native`], is almost always what you want. [`Watcher::Poll`] exists
+/// for cases where native watching isn't an option, e.g. network filesystems (NFS/SMB), some
+/// containerized environments, or platforms where inotify/FSEvents are known to misbehave.
+#[derive(Clone, Copy, Debug)]
+pub enum Watcher {
+    /// Use the platform's native watcher (inotify, FSEvents, ReadDirectoryChangesW, etc).
+    Native,
+    /// Poll the filesystem for changes on the given interval instead of relying on OS events.
+    Poll(Duration),
+}
+
+impl Default for Watcher {
+    fn default() -> Self {
+        Self::Native
+    }
+}
+
+/// The debouncer used internally by [`Hotwatch`] and [`blocking::Hotwatch`].
+///
+/// See [`debounce`] for why this isn't `notify_debouncer_full::Debouncer`.
+pub(crate) type AnyDebouncer = debounce::Debouncer;
+
 #[derive(Debug)]
 pub enum Error {
     Io(std::io::Error),
     Notify(notify::Error),
+    Glob(glob::PatternError),
 }
 
 impl std::fmt::Display for Error {
@@ -39,6 +69,7 @@ impl std::fmt::Display for Error {
         match self {
             Self::Io(error) => error.fmt(fmt),
             Self::Notify(error) => error.fmt(fmt),
+            Self::Glob(error) => error.fmt(fmt),
         }
     }
 }
@@ -48,6 +79,7 @@ impl std::error::Error for Error {
         match self {
             Self::Io(error) => error.source(),
             Self::Notify(error) => error.source(),
+            Self::Glob(error) => error.source(),
         }
     }
 }
@@ -68,7 +100,13 @@ impl From<notify::Error> for Error {
     }
 }
 
-type HandlerMap = HashMap<PathBuf, Box<dyn FnMut(Event) + Send>>;
+impl From<glob::PatternError> for Error {
+    fn from(err: glob::PatternError) -> Self {
+        Self::Glob(err)
+    }
+}
+
+type HandlerMap = util::Handlers<Box<dyn FnMut(Event, &Path) + Send>>;
 
 /// A non-blocking hotwatch instance.
 ///
@@ -77,7 +115,7 @@ type HandlerMap = HashMap<PathBuf, Box<dyn FnMut(Event) + Send>>;
 ///
 /// Dropping this will also unwatch everything.
 pub struct Hotwatch {
-    debouncer: Debouncer<notify::RecommendedWatcher, FileIdMap>,
+    debouncer: AnyDebouncer,
     handlers: Arc<Mutex<HandlerMap>>,
 }
 
@@ -113,10 +151,17 @@ impl Hotwatch {
     ///
     /// A delay of over 30 seconds will prevent repetitions of previous events on macOS.
     pub fn new_with_custom_delay(delay: std::time::Duration) -> Result<Self, Error> {
+        Self::new_with_config(delay, Watcher::default())
+    }
+
+    /// Like [`Hotwatch::new_with_custom_delay`], but also lets you choose the [`Watcher`]
+    /// backend, e.g. to force polling on a network filesystem where native watching doesn't
+    /// work.
+    pub fn new_with_config(delay: std::time::Duration, watcher: Watcher) -> Result<Self, Error> {
         let (tx, rx) = channel();
         let handlers = Arc::<Mutex<_>>::default();
         Self::run(Arc::clone(&handlers), rx);
-        let debouncer = new_debouncer(delay, None, tx).map_err(Error::Notify)?;
+        let debouncer = AnyDebouncer::new(delay, watcher, tx).map_err(Error::Notify)?;
         Ok(Self {
             debouncer,
             handlers,
@@ -130,7 +175,8 @@ impl Hotwatch {
     ///
     /// Only the most specific applicable handler will be called. In other words, if you're
     /// watching "dir" and "dir/file1", then only the latter handler will fire for changes to
-    /// `file1`.
+    /// `file1`. Alongside the event, the handler receives the root it was registered under, so a
+    /// single recursive handler can tell which of its roots an event came from.
     ///
     /// Note that handlers will be run in hotwatch's watch thread, so you'll have to use `move`
     /// if the closure captures anything.
@@ -145,29 +191,138 @@ impl Hotwatch {
     /// use hotwatch::{notify::event::ModifyKind, Hotwatch, Event, EventKind};
     ///
     /// let mut hotwatch = Hotwatch::new().expect("hotwatch failed to initialize!");
-    /// hotwatch.watch("README.md", |event: Event| {
+    /// hotwatch.watch("README.md", |event: Event, root| {
     ///     if let EventKind::Modify(ModifyKind::Data(_)) = event.kind {
-    ///         println!("{:?} changed!", event.paths[0]);
+    ///         println!("{:?} changed, under {root:?}!", event.paths[0]);
     ///     }
     /// }).expect("failed to watch file!");
     /// ```
     pub fn watch<P, F>(&mut self, path: P, handler: F) -> Result<(), Error>
     where
         P: AsRef<Path>,
-        F: 'static + FnMut(Event) + Send,
+        F: 'static + FnMut(Event, &Path) + Send,
+    {
+        self.watch_with_mode(path, RECURSIVE_MODE, handler)
+    }
+
+    /// Like [`Self::watch`], but only watches the immediate contents of a directory instead of
+    /// recursing into its subdirectories.
+    ///
+    /// This is useful for watching large directories (e.g. a downloads folder) where you don't
+    /// want to pay the cost of traversing every descendant.
+    ///
+    /// # Errors
+    ///
+    /// Watching will fail if the path can't be read, returning [`Error::Io`].
+    pub fn watch_non_recursive<P, F>(&mut self, path: P, handler: F) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        F: 'static + FnMut(Event, &Path) + Send,
+    {
+        self.watch_with_mode(path, notify::RecursiveMode::NonRecursive, handler)
+    }
+
+    /// Like [`Self::watch`], but lets you choose the [`notify::RecursiveMode`] used for this
+    /// path explicitly.
+    ///
+    /// # Errors
+    ///
+    /// Watching will fail if the path can't be read, returning [`Error::Io`].
+    pub fn watch_with_mode<P, F>(
+        &mut self,
+        path: P,
+        mode: notify::RecursiveMode,
+        handler: F,
+    ) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        F: 'static + FnMut(Event, &Path) + Send,
     {
         let absolute_path = path.as_ref().canonicalize()?;
-        self.debouncer
-            .watcher()
-            .watch(&absolute_path, RECURSIVE_MODE)?;
-        self.debouncer
-            .cache()
-            .add_root(&absolute_path, RECURSIVE_MODE);
+        self.debouncer.watch(&absolute_path, mode)?;
         let mut handlers = self.handlers.lock().expect("handler mutex poisoned!");
         handlers.insert(absolute_path, Box::new(handler));
         Ok(())
     }
 
+    /// Like [`Self::watch`], but returns a [`Stream`](tokio_stream::Stream) of `(Event, PathBuf)`
+    /// pairs for the path instead of registering a callback handler, where the `PathBuf` is the
+    /// root the event matched.
+    ///
+    /// Callback handlers run on hotwatch's background thread, which is why [`Self::watch`]
+    /// requires `move` and makes accessing outside data awkward. A stream sidesteps that: events
+    /// are forwarded to whichever task polls it, so you can `while let Some((event, root)) =
+    /// stream.next().await` from your own async code.
+    ///
+    /// Gated behind the `tokio` feature.
+    ///
+    /// # Errors
+    ///
+    /// Watching will fail if the path can't be read, returning [`Error::Io`].
+    #[cfg(feature = "tokio")]
+    pub fn watch_stream<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<tokio_stream::wrappers::UnboundedReceiverStream<(Event, PathBuf)>, Error> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.watch(path, move |event, root| {
+            let _ = tx.send((event, root.to_path_buf()));
+        })?;
+        Ok(tokio_stream::wrappers::UnboundedReceiverStream::new(rx))
+    }
+
+    /// Register a handler for paths matching a glob, e.g. `src/**/*.rs`.
+    ///
+    /// Glob handlers are only consulted once no exact root (as registered via [`Self::watch`])
+    /// matches an event's path, and are tried in the order they were registered. Register
+    /// ignore patterns with [`Self::ignore`] to keep paths away from glob handlers, e.g.
+    /// `target/**`.
+    ///
+    /// Event paths are always absolute, so a relative-looking pattern like `src/**/*.rs` is
+    /// matched against the event's path relative to the current working directory; patterns
+    /// that are themselves absolute are matched as-is.
+    ///
+    /// Since there's no single concrete directory backing a glob, the handler receives the glob
+    /// pattern itself as its root.
+    ///
+    /// Note that this only registers the handler and doesn't establish a filesystem watch;
+    /// events only arrive for paths watched via [`Self::watch`] or its variants. Those register
+    /// an exact root, and an exact root matches every path at or beneath it before any glob is
+    /// tried, so an event under a directory watched that way goes to that directory's handler,
+    /// not to a glob handler.
+    ///
+    /// # Errors
+    ///
+    /// This will fail if `pattern` isn't a valid glob, returning [`Error::Glob`].
+    pub fn watch_glob<F>(&mut self, pattern: &str, handler: F) -> Result<(), Error>
+    where
+        F: 'static + FnMut(Event, &Path) + Send,
+    {
+        let pattern = glob::Pattern::new(pattern)?;
+        let mut handlers = self.handlers.lock().expect("handler mutex poisoned!");
+        handlers.insert_glob(pattern, Box::new(handler));
+        Ok(())
+    }
+
+    /// Exclude paths matching a glob from triggering a [`Self::watch_glob`] handler, e.g.
+    /// `target/**`.
+    ///
+    /// Like [`Self::watch_glob`], the pattern is matched against the event's path relative to
+    /// the current working directory, falling back to the absolute path.
+    ///
+    /// Ignore patterns only suppress the glob fallback; an exact root registered via
+    /// [`Self::watch`] still wins, since exact roots are always the most specific match.
+    ///
+    /// # Errors
+    ///
+    /// This will fail if `pattern` isn't a valid glob, returning [`Error::Glob`].
+    pub fn ignore(&mut self, pattern: &str) -> Result<(), Error> {
+        let pattern = glob::Pattern::new(pattern)?;
+        let mut handlers = self.handlers.lock().expect("handler mutex poisoned!");
+        handlers.ignore(pattern);
+        Ok(())
+    }
+
     /// Stop watching a path.
     ///
     /// # Errors
@@ -176,13 +331,22 @@ impl Hotwatch {
     /// couldn't be unwatched for some platform-specific internal reason.
     pub fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
         let absolute_path = path.as_ref().canonicalize()?;
-        self.debouncer.watcher().unwatch(&absolute_path)?;
-        self.debouncer.cache().remove_root(&absolute_path);
+        self.debouncer.unwatch(&absolute_path)?;
         let mut handlers = self.handlers.lock().expect("handler mutex poisoned!");
         handlers.remove(&absolute_path);
         Ok(())
     }
 
+    /// Immediately dispatch any events currently buffered by the debounce delay, instead of
+    /// waiting for the remaining delay to elapse.
+    ///
+    /// This is useful when you know it's safe to process changes right now and don't want to
+    /// hold onto stale state or race a file lock. Handlers still run on the background thread,
+    /// same as usual.
+    pub fn flush(&mut self) {
+        self.debouncer.flush();
+    }
+
     fn run(handlers: Arc<Mutex<HandlerMap>>, rx: Receiver<DebounceEventResult>) {
         std::thread::spawn(move || loop {
             match rx.recv() {
@@ -191,8 +355,10 @@ impl Hotwatch {
                         for event in events {
                             util::log_event(&event);
                             let mut handlers = handlers.lock().expect("handler mutex poisoned!");
-                            if let Some(handler) = util::handler_for_event(&event, &mut handlers) {
-                                handler(event);
+                            if let Some((handler, root)) =
+                                util::handler_for_event(&event, &mut handlers)
+                            {
+                                handler(event, &root);
                             }
                         }
                     }
@@ -210,3 +376,74 @@ impl Hotwatch {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, sync::atomic::AtomicBool};
+
+    #[test]
+    fn poll_backend_still_dispatches_events() {
+        let dir = std::env::temp_dir().join(format!("hotwatch-poll-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("failed to create test dir");
+        let file = dir.join("watched.txt");
+        fs::write(&file, "initial").expect("failed to write test file");
+
+        let mut hotwatch = Hotwatch::new_with_config(
+            Duration::from_millis(50),
+            Watcher::Poll(Duration::from_millis(50)),
+        )
+        .expect("hotwatch failed to initialize with the poll backend");
+        let fired = Arc::new(AtomicBool::new(false));
+        {
+            let fired = Arc::clone(&fired);
+            hotwatch
+                .watch(&file, move |_event, _root| {
+                    fired.store(true, std::sync::atomic::Ordering::SeqCst);
+                })
+                .expect("failed to watch file");
+        }
+
+        // The poll backend only notices changes on its own interval, so retry the write
+        // instead of hoping a single one lands inside some guessed window.
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !fired.load(std::sync::atomic::Ordering::SeqCst) && std::time::Instant::now() < deadline
+        {
+            fs::write(&file, "changed").expect("failed to update test file");
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+
+        assert!(
+            fired.load(std::sync::atomic::Ordering::SeqCst),
+            "the poll watcher backend should still notice and dispatch file changes"
+        );
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn watch_stream_forwards_matched_events() {
+        use tokio_stream::StreamExt as _;
+
+        let dir = std::env::temp_dir().join(format!("hotwatch-stream-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("failed to create test dir");
+        let file = dir.join("watched.txt");
+        fs::write(&file, "initial").expect("failed to write test file");
+
+        let mut hotwatch = Hotwatch::new_with_custom_delay(Duration::from_millis(50))
+            .expect("hotwatch failed to initialize");
+        let mut stream = hotwatch.watch_stream(&file).expect("failed to watch file");
+
+        fs::write(&file, "changed").expect("failed to update test file");
+
+        let (_event, root) = tokio::time::timeout(std::time::Duration::from_secs(5), stream.next())
+            .await
+            .expect("timed out waiting for a stream event")
+            .expect("stream ended without an event");
+
+        assert_eq!(root, file.canonicalize().expect("failed to canonicalize test path"));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
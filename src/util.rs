@@ -27,28 +27,200 @@ pub fn log_dead() {
     log::debug!("sender disconnected! the watcher is dead 💀");
 }
 
+/// Handlers registered against exact roots, in addition to glob patterns and ignore patterns.
+///
+/// Exact roots always take priority over glob patterns, so that "most specific wins" still holds
+/// when both kinds of registration apply to the same path.
+pub struct Handlers<H> {
+    exact: HashMap<PathBuf, H>,
+    globs: Vec<(glob::Pattern, H)>,
+    ignores: Vec<glob::Pattern>,
+}
+
+impl<H> Default for Handlers<H> {
+    fn default() -> Self {
+        Self {
+            exact: HashMap::new(),
+            globs: Vec::new(),
+            ignores: Vec::new(),
+        }
+    }
+}
+
+impl<H> Handlers<H> {
+    pub fn insert(&mut self, path: PathBuf, handler: H) {
+        self.exact.insert(path, handler);
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Option<H> {
+        self.exact.remove(path)
+    }
+
+    pub fn insert_glob(&mut self, pattern: glob::Pattern, handler: H) {
+        self.globs.push((pattern, handler));
+    }
+
+    pub fn ignore(&mut self, pattern: glob::Pattern) {
+        self.ignores.push(pattern);
+    }
+}
+
+/// Finds the handler that should run for `e`, along with the [`WatchedPath`] root it was
+/// registered under, so that a single recursive handler can tell which of its roots an event
+/// actually came from without re-deriving it from `e.paths`.
 pub fn handler_for_event<'a, H>(
     e: &Event,
-    handlers: &'a mut HashMap<PathBuf, H>,
-) -> Option<&'a mut H> {
+    handlers: &'a mut Handlers<H>,
+) -> Option<(&'a mut H, WatchedPath)> {
     fn path_from_event(e: &Event) -> Option<&PathBuf> {
         e.paths.first()
     }
 
-    fn find_handler<'a, H>(
+    fn find_exact<'a, H>(
         path: &Path,
-        handlers: &'a mut HashMap<PathBuf, H>,
-    ) -> Option<&'a mut H> {
+        exact: &'a mut HashMap<PathBuf, H>,
+    ) -> Option<(&'a mut H, PathBuf)> {
         let mut remaining_path = Some(path);
         while let Some(path) = remaining_path {
             log_matching_path(path);
-            if handlers.contains_key(path) {
-                return handlers.get_mut(path);
+            if exact.contains_key(path) {
+                return exact.get_mut(path).map(|handler| (handler, path.to_path_buf()));
             }
             remaining_path = path.parent();
         }
         None
     }
 
-    path_from_event(e).and_then(move |path| find_handler(path, handlers))
+    fn find_glob<'a, H>(
+        path: &Path,
+        globs: &'a mut [(glob::Pattern, H)],
+    ) -> Option<(&'a mut H, PathBuf)> {
+        let relative = relative_to_cwd(path);
+        globs
+            .iter_mut()
+            .find(|(pattern, _)| pattern_matches(pattern, path, relative.as_deref()))
+            .map(|(pattern, handler)| (handler, PathBuf::from(pattern.as_str())))
+    }
+
+    let path = path_from_event(e)?;
+    // Exact roots are the most specific kind of registration, so they always win, even over an
+    // ignore pattern that would otherwise suppress a glob handler for the same path.
+    if let Some(found) = find_exact(path, &mut handlers.exact) {
+        return Some(found).map(|(handler, root)| (handler, WatchedPath(root)));
+    }
+    let relative = relative_to_cwd(path);
+    if handlers
+        .ignores
+        .iter()
+        .any(|pattern| pattern_matches(pattern, path, relative.as_deref()))
+    {
+        return None;
+    }
+    find_glob(path, &mut handlers.globs).map(|(handler, root)| (handler, WatchedPath(root)))
+}
+
+/// `path` is always absolute (handlers are registered against canonicalized paths), but
+/// glob patterns like `src/**/*.rs` are naturally written relative to the project root. Since
+/// `glob::Pattern` matching is anchored at the start of the string, try the path relative to the
+/// current working directory first, falling back to the absolute path for patterns that are
+/// meant to be absolute.
+fn relative_to_cwd(path: &Path) -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    path.strip_prefix(cwd).ok().map(PathBuf::from)
+}
+
+fn pattern_matches(pattern: &glob::Pattern, path: &Path, relative: Option<&Path>) -> bool {
+    relative.is_some_and(|relative| pattern.matches_path(relative)) || pattern.matches_path(path)
+}
+
+/// The root a handler was registered under, as determined by [`handler_for_event`].
+///
+/// For an exact root (registered via `watch`/`watch_with_mode`) this is that root itself; for a
+/// glob handler (registered via `watch_glob`) this is the glob pattern, since there's no single
+/// concrete directory to point to.
+#[derive(Debug)]
+pub struct WatchedPath(PathBuf);
+
+impl std::ops::Deref for WatchedPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for WatchedPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event_for(path: &Path) -> Event {
+        Event {
+            event: notify::Event::new(notify::EventKind::Any).add_path(path.to_path_buf()),
+            time: std::time::Instant::now(),
+        }
+    }
+
+    #[test]
+    fn exact_handler_for_a_grandchild_path_returns_its_registered_ancestor_root() {
+        let root = PathBuf::from("/tmp/hotwatch-test-project/dir");
+        let path = root.join("sub").join("file.txt");
+        let mut handlers = Handlers::default();
+        handlers.insert(root.clone(), "dir-handler");
+
+        let event = event_for(&path);
+        let (handler, matched_root) =
+            handler_for_event(&event, &mut handlers).expect("ancestor root should match");
+        assert_eq!(*handler, "dir-handler");
+        assert_eq!(
+            &*matched_root,
+            root.as_path(),
+            "the handler should receive the registered root, not the event's own path"
+        );
+    }
+
+    #[test]
+    fn exact_handler_wins_over_an_ignore_pattern_on_the_same_path() {
+        let path = PathBuf::from("/tmp/hotwatch-test-project/target/important.txt");
+        let mut handlers = Handlers::default();
+        handlers.insert(path.clone(), "exact");
+        handlers.ignore(glob::Pattern::new("/tmp/hotwatch-test-project/target/**").unwrap());
+
+        let event = event_for(&path);
+        let (handler, root) = handler_for_event(&event, &mut handlers).expect("exact root should still win");
+        assert_eq!(*handler, "exact");
+        assert_eq!(&*root, path.as_path());
+    }
+
+    #[test]
+    fn ignore_pattern_still_suppresses_the_glob_fallback() {
+        let path = PathBuf::from("/tmp/hotwatch-test-project/target/main.rs");
+        let mut handlers = Handlers::default();
+        handlers.insert_glob(
+            glob::Pattern::new("/tmp/hotwatch-test-project/**/*.rs").unwrap(),
+            "glob",
+        );
+        handlers.ignore(glob::Pattern::new("/tmp/hotwatch-test-project/target/**").unwrap());
+
+        let event = event_for(&path);
+        assert!(handler_for_event(&event, &mut handlers).is_none());
+    }
+
+    #[test]
+    fn glob_pattern_matches_an_absolute_event_path_relative_to_cwd() {
+        let cwd = std::env::current_dir().expect("test requires a readable cwd");
+        let path = cwd.join("src").join("lib.rs");
+        let mut handlers = Handlers::default();
+        handlers.insert_glob(glob::Pattern::new("src/**/*.rs").unwrap(), "glob");
+
+        let event = event_for(&path);
+        let (handler, root) = handler_for_event(&event, &mut handlers).expect("relative glob should match");
+        assert_eq!(*handler, "glob");
+        assert_eq!(&*root, Path::new("src/**/*.rs"));
+    }
 }